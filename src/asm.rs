@@ -0,0 +1,231 @@
+// Two-way mapping between the line-oriented mnemonic syntax and the
+// 16-bit instruction encoding used by `Machine`/`Instruction`.
+//
+// Mnemonic, opcode and operand-layout assignments all come from
+// `opcode_table::OPCODE_TABLE`, the same table `Machine::execute` dispatches
+// through, so the assembler and the dispatch table never drift apart.
+use std::collections::HashMap;
+
+use crate::instruction::Instruction;
+use crate::opcode_table::{self, OperandShape};
+
+const PROGRAM_START: u16 = 512;
+
+// assemble a program from mnemonic source text into the raw bytes
+// `Machine::load` expects.
+//
+// labels are resolved in a first pass that walks the source computing
+// each instruction's address (starting at PROGRAM_START), then a second
+// pass emits the encoded bytes, looking up any label operands.
+pub fn assemble(source: &str) -> Result<Vec<u8>, String> {
+    let lines = strip_comments(source);
+    let labels = collect_labels(&lines)?;
+
+    let mut bytes = Vec::new();
+    for line in &lines {
+        if let Some(mnemonic_line) = after_label(line) {
+            if mnemonic_line.is_empty() {
+                continue;
+            }
+            let instr = encode_line(mnemonic_line, &labels)?;
+            bytes.extend_from_slice(&instr.as_bytes());
+        }
+    }
+    Ok(bytes)
+}
+
+// decode a loaded program back into mnemonics, one per instruction, via
+// the same opcode table `dispatch` uses.
+pub fn disassemble(program: &[u8]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i + 1 < program.len() {
+        let instr = Instruction::from_bytes([program[i], program[i + 1]]);
+        out.push(decode_line(instr));
+        i += 2;
+    }
+    out
+}
+
+fn strip_comments(source: &str) -> Vec<String> {
+    source
+        .lines()
+        .map(|line| line.split(';').next().unwrap_or("").trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+// a line is either `label:` on its own, `label: mnemonic ...`, or
+// just `mnemonic ...`. returns the mnemonic portion, if any.
+fn after_label(line: &str) -> Option<&str> {
+    match line.split_once(':') {
+        Some((_, rest)) => Some(rest.trim()),
+        None => Some(line),
+    }
+}
+
+fn collect_labels(lines: &[String]) -> Result<HashMap<String, u16>, String> {
+    let mut labels = HashMap::new();
+    let mut addr = PROGRAM_START;
+    for line in lines {
+        if let Some((label, rest)) = line.split_once(':') {
+            let label = label.trim();
+            if labels.insert(label.to_string(), addr).is_some() {
+                return Err(format!("duplicate label: {}", label));
+            }
+            if rest.trim().is_empty() {
+                continue;
+            }
+        }
+        addr += 2;
+    }
+    Ok(labels)
+}
+
+fn encode_line(line: &str, labels: &HashMap<String, u16>) -> Result<Instruction, String> {
+    let mut tokens = line.split_whitespace();
+    let mnemonic = tokens.next().ok_or("empty instruction")?;
+    let args: Vec<&str> = tokens.collect();
+
+    let def = opcode_table::lookup_mnemonic(mnemonic).ok_or(format!("unknown mnemonic: {}", mnemonic))?;
+    let opcode = def.opcode as u16;
+    let word = match def.shape {
+        OperandShape::None { filler } => (opcode << 12) | filler,
+        OperandShape::Addr => encode_nnn(opcode, addr_arg(&args, 0, labels)?),
+        OperandShape::RegByte => encode_xkk(opcode, reg_arg(&args, 0)?, byte_arg(&args, 1)?),
+        OperandShape::RegReg => {
+            encode_xyn(opcode, reg_arg(&args, 0)?, reg_arg(&args, 1)?, def.n.unwrap_or(0))
+        }
+        OperandShape::Reg => encode_xyn(opcode, reg_arg(&args, 0)?, 0x0, def.n.unwrap_or(0)),
+    };
+    Ok(Instruction(word))
+}
+
+fn decode_line(instr: Instruction) -> String {
+    let def = match opcode_table::lookup(instr.opcode() as u8, instr.n()) {
+        Some(def) => def,
+        None => return format!("; unknown {:04x}", instr.0),
+    };
+    match def.shape {
+        OperandShape::None { .. } => def.mnemonic.to_string(),
+        OperandShape::Addr => format!("{} 0x{:03x}", def.mnemonic, instr.nnn()),
+        OperandShape::RegByte => format!("{} v{:x} 0x{:02x}", def.mnemonic, instr.x(), instr.kk()),
+        OperandShape::RegReg => format!("{} v{:x} v{:x}", def.mnemonic, instr.x(), instr.y()),
+        OperandShape::Reg => format!("{} v{:x}", def.mnemonic, instr.x()),
+    }
+}
+
+fn encode_nnn(opcode: u16, nnn: u16) -> u16 {
+    (opcode << 12) | (nnn & 0x0fff)
+}
+
+fn encode_xkk(opcode: u16, x: u8, kk: u8) -> u16 {
+    (opcode << 12) | ((x as u16) << 8) | kk as u16
+}
+
+fn encode_xyn(opcode: u16, x: u8, y: u8, n: u8) -> u16 {
+    (opcode << 12) | ((x as u16) << 8) | ((y as u16) << 4) | n as u16
+}
+
+fn reg_arg(args: &[&str], idx: usize) -> Result<u8, String> {
+    let tok = args.get(idx).ok_or("missing register argument")?;
+    let digits = tok.strip_prefix(['v', 'V']).ok_or(format!("expected register, got {}", tok))?;
+    let reg = u8::from_str_radix(digits, 16).map_err(|_| format!("invalid register: {}", tok))?;
+    if reg > 0xF {
+        return Err(format!("register out of range (v0-vF): {}", tok));
+    }
+    Ok(reg)
+}
+
+fn byte_arg(args: &[&str], idx: usize) -> Result<u8, String> {
+    let tok = args.get(idx).ok_or("missing byte argument")?;
+    parse_number(tok).and_then(|n| u8::try_from(n).map_err(|_| format!("byte out of range: {}", tok)))
+}
+
+fn addr_arg(args: &[&str], idx: usize, labels: &HashMap<String, u16>) -> Result<u16, String> {
+    let tok = *args.get(idx).ok_or("missing address argument")?;
+    if let Some(&addr) = labels.get(tok) {
+        return if addr <= 0x0fff {
+            Ok(addr)
+        } else {
+            Err(format!("label out of range: {} (0x{:04x})", tok, addr))
+        };
+    }
+    let n = parse_number(tok)?;
+    u16::try_from(n)
+        .ok()
+        .filter(|addr| *addr <= 0x0fff)
+        .ok_or(format!("address out of range: {}", tok))
+}
+
+fn parse_number(tok: &str) -> Result<u32, String> {
+    if let Some(hex) = tok.strip_prefix("0x").or_else(|| tok.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).map_err(|_| format!("invalid number: {}", tok))
+    } else {
+        tok.parse::<u32>().map_err(|_| format!("invalid number: {}", tok))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assemble_round_trips_through_disassemble() {
+        let source = "\
+            ld v0 0x11\n\
+            ld v1 0x22\n\
+            add_xy v0 v1\n\
+            exit\n";
+        let bytes = assemble(source).expect("valid source should assemble");
+        let lines = disassemble(&bytes);
+        assert_eq!(
+            lines,
+            vec!["ld v0 0x11", "ld v1 0x22", "add_xy v0 v1", "exit"]
+        );
+    }
+
+    #[test]
+    fn assemble_resolves_forward_and_backward_labels() {
+        let source = "\
+            jmp end\n\
+            loop: add v0 0x01\n\
+            jmp loop\n\
+            end: exit\n";
+        let bytes = assemble(source).expect("labels should resolve");
+        let lines = disassemble(&bytes);
+        assert_eq!(
+            lines,
+            vec!["jmp 0x206", "add v0 0x01", "jmp 0x202", "exit"]
+        );
+    }
+
+    #[test]
+    fn assemble_rejects_out_of_range_register() {
+        let err = assemble("ld v16 0x05").unwrap_err();
+        assert!(err.contains("out of range"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn assemble_rejects_label_past_0xfff() {
+        let mut source = String::new();
+        for _ in 0..2100 {
+            source.push_str("add v0 0x01\n");
+        }
+        source.push_str("target: exit\njmp target\n");
+        let err = assemble(&source).unwrap_err();
+        assert!(err.contains("out of range"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn assemble_rejects_unknown_mnemonic() {
+        let err = assemble("frobnicate v0").unwrap_err();
+        assert!(err.contains("unknown mnemonic"), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn assemble_rejects_duplicate_label() {
+        let err = assemble("a: exit\na: exit\n").unwrap_err();
+        assert!(err.contains("duplicate label"), "unexpected error: {}", err);
+    }
+}
@@ -1,8 +1,51 @@
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
 use crate::instruction::Instruction;
+use crate::memory::Memory;
+use crate::opcode_table::{self, OpcodeDef, OperandShape};
+
+// timers decrement at a fixed 60 Hz, independent of instruction throughput
+const TIMER_PERIOD: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// programs are loaded at 0x200, leaving the first 512 bytes free
+const PROGRAM_START: u16 = 512;
+
+// selects the machine's memory size; defaults to the original 4 KB so
+// existing callers keep working unchanged
+pub struct MachineConfig {
+    pub memory_size: usize,
+}
+
+impl Default for MachineConfig {
+    fn default() -> Self {
+        MachineConfig { memory_size: 4096 }
+    }
+}
+
+// how the machine came to stop running
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum HaltStatus {
+    // the program executed the exit instruction
+    Exited,
+    // execution was paused (e.g. by a breakpoint) and can be resumed
+    Halted,
+}
+
+// recoverable faults raised while executing a program, in place of a
+// panic or a raw process exit
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RuntimeError {
+    PcOutOfBounds(u16),
+    MemoryAccess(u16),
+    StackOverflow,
+    StackUnderflow,
+    UnknownInstruction(u16),
+}
 
 pub struct Machine {
-    // 4kb of memory
-    mem: [u8; 4096],
+    // paged, configurably-sized memory
+    mem: Memory,
     // 16 8-bit registers
     // reg 15 is used as a flag
     regs: [u8; 16],
@@ -14,39 +57,148 @@ pub struct Machine {
     sp: u8,
     // 16 16-bit values
     stack: [u16; 16],
+    // delay timer, decremented at 60 Hz down to 0
+    dt: u8,
+    // sound timer, decremented at 60 Hz down to 0; nonzero means "beeping"
+    st: u8,
+    // real time accumulated toward the next 60 Hz timer tick
+    timer_accum: Duration,
+    // instructions-per-second throttle for `run`; `None` means unthrottled
+    ips_cap: Option<u32>,
+    // addresses that pause `run` before the instruction there executes
+    breakpoints: HashSet<u16>,
+    // opt-in instruction tracing, printed from `dispatch` and `step`
+    trace: bool,
 }
 
 impl Machine {
-    pub fn new() -> Self {
+    pub fn new(config: MachineConfig) -> Self {
         Machine {
-            mem: [0; 4096],
+            mem: Memory::new(config.memory_size),
             regs: [0; 16],
             i: 0,
-            pc: 512,
+            pc: PROGRAM_START,
             sp: 0,
             stack: [0; 16],
+            dt: 0,
+            st: 0,
+            timer_accum: Duration::ZERO,
+            ips_cap: None,
+            breakpoints: HashSet::new(),
+            trace: false,
         }
     }
 
+    // cap how many instructions `run` executes per second; `None` (or
+    // `Some(0)`, which would otherwise divide by zero below) removes the
+    // cap and lets it run unthrottled
+    pub fn set_ips_cap(&mut self, ips: Option<u32>) {
+        self.ips_cap = ips.filter(|&ips| ips > 0);
+    }
+
+    // whether the sound timer is currently nonzero, i.e. a host should
+    // be driving audio
+    pub fn sound_active(&self) -> bool {
+        self.st > 0
+    }
+
+    // pause `run` just before the instruction at `addr` executes
+    pub fn add_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.insert(addr);
+    }
+
+    pub fn remove_breakpoint(&mut self, addr: u16) {
+        self.breakpoints.remove(&addr);
+    }
+
+    // opt-in tracing of every fetched instruction, off by default
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace = trace;
+    }
+
     // load a program into memory
     // programs start after the first page
-    pub fn load(&mut self, program: &[u8]) {
-        for (i, &byte) in program.iter().enumerate() {
-            self.mem[i + 512] = byte;
-        }
+    pub fn load(&mut self, program: &[u8]) -> Result<(), RuntimeError> {
+        self.mem.write_slice(PROGRAM_START, program)
     }
 
-    pub fn run(&mut self) {
+    // run until the program exits, hits a breakpoint, or a runtime error
+    // occurs, throttled to `ips_cap` instructions per second if set
+    pub fn run(&mut self) -> Result<HaltStatus, RuntimeError> {
+        let mut last = Instant::now();
         loop {
-            self.dispatch();
+            if self.breakpoints.contains(&self.pc) {
+                return Ok(HaltStatus::Halted);
+            }
+            if let Some(ips) = self.ips_cap {
+                let period = Duration::from_secs_f64(1.0 / ips as f64);
+                let elapsed = last.elapsed();
+                if elapsed < period {
+                    std::thread::sleep(period - elapsed);
+                }
+            }
+            let now = Instant::now();
+            let elapsed = now.duration_since(last);
+            last = now;
+            if let Some(status) = self.step_with_clock(elapsed)? {
+                return Ok(status);
+            }
+        }
+    }
+
+    // decrement dt/st for the real time that has elapsed since the last
+    // call, then execute exactly one instruction
+    pub fn step_with_clock(&mut self, elapsed: Duration) -> Result<Option<HaltStatus>, RuntimeError> {
+        self.tick_timers(elapsed);
+        self.dispatch()
+    }
+
+    fn tick_timers(&mut self, elapsed: Duration) {
+        self.timer_accum += elapsed;
+        while self.timer_accum >= TIMER_PERIOD {
+            self.timer_accum -= TIMER_PERIOD;
+            self.dt = self.dt.saturating_sub(1);
+            self.st = self.st.saturating_sub(1);
+        }
+    }
+
+    // execute exactly one instruction regardless of breakpoints, returning
+    // the instruction that was decoded so a caller can inspect it
+    pub fn step(&mut self) -> Result<Instruction, RuntimeError> {
+        let instr = self.fetch()?;
+        if self.trace {
+            println!("{:X}", instr.0);
+        }
+        self.execute(instr)?;
+        Ok(instr)
+    }
+
+    // print all registers (flagging regs[0xF]), i, pc, sp, and the active
+    // stack frames
+    pub fn dump(&self) {
+        println!("pc: {:04x}  i: {:04x}  sp: {:02x}", self.pc, self.i, self.sp);
+        for (idx, reg) in self.regs.iter().enumerate() {
+            if idx == 0xF {
+                println!("  v{:X}: {:02x} (flag)", idx, reg);
+            } else {
+                println!("  v{:X}: {:02x}", idx, reg);
+            }
+        }
+        println!("stack:");
+        for frame in 0..self.sp as usize {
+            println!("  #{}: {:04x}", frame, self.stack[frame + 1]);
         }
     }
 
     // 0x0000
     // return from subroutine
-    fn ret(&mut self) {
+    fn ret(&mut self) -> Result<(), RuntimeError> {
+        if self.sp == 0 {
+            return Err(RuntimeError::StackUnderflow);
+        }
         self.pc = self.stack[self.sp as usize];
         self.sp -= 1;
+        Ok(())
     }
 
     // 0x1nnn
@@ -57,10 +209,14 @@ impl Machine {
 
     // 0x2nnn
     // call subroutine at nnn
-    fn call(&mut self, addr: u16) {
+    fn call(&mut self, addr: u16) -> Result<(), RuntimeError> {
+        if self.sp as usize + 1 >= self.stack.len() {
+            return Err(RuntimeError::StackOverflow);
+        }
         self.sp += 1;
         self.stack[self.sp as usize] = self.pc;
         self.pc = addr;
+        Ok(())
     }
 
     // 0x3xkk
@@ -90,6 +246,45 @@ impl Machine {
         }
     }
 
+    // 0x5xy1
+    // compare reg x to reg y as signed 8-bit values
+    // skip next instruction if reg x < reg y
+    //
+    // the request this implements asked for a jump to nnn, but the
+    // 0x5xy_ nibble space has no room left for a 12-bit address once x
+    // and y occupy their nibbles. named and shaped like eq_xy/neq_xy
+    // (skip-on-condition) instead of jmp, to avoid implying an address
+    // operand that doesn't exist here.
+    fn lt_xy(&mut self, x: u8, y: u8) {
+        if (self.regs[x as usize] as i8) < (self.regs[y as usize] as i8) {
+            self.pc += 2;
+        }
+    }
+
+    // 0x5xy2
+    // compare reg x to reg y as signed 8-bit values
+    // skip next instruction if reg x > reg y
+    // (see lt_xy for why this skips instead of jumping to an address)
+    fn gt_xy(&mut self, x: u8, y: u8) {
+        if (self.regs[x as usize] as i8) > (self.regs[y as usize] as i8) {
+            self.pc += 2;
+        }
+    }
+
+    // 0x5xy3
+    // compare reg x to reg y as signed 8-bit values
+    // set reg 0xF to 0 (less), 1 (equal), or 2 (greater), mirroring the
+    // carry/borrow convention of add_xy/sub_xy: the flag always reports
+    // the outcome, never the operands
+    fn cmp_xy(&mut self, x: u8, y: u8) {
+        let ord = (self.regs[x as usize] as i8).cmp(&(self.regs[y as usize] as i8));
+        self.regs[0xF] = match ord {
+            std::cmp::Ordering::Less => 0,
+            std::cmp::Ordering::Equal => 1,
+            std::cmp::Ordering::Greater => 2,
+        };
+    }
+
     // 0x6xkk
     // set reg x to kk
     fn ld(&mut self, x: u8, byte: u8) {
@@ -184,6 +379,26 @@ impl Machine {
         }
     }
 
+    // 0x9xy1
+    // compare reg x to reg y as unsigned 8-bit values
+    // skip next instruction if reg x < reg y
+    // (see lt_xy for why this skips instead of jumping to an address)
+    fn ltu_xy(&mut self, x: u8, y: u8) {
+        if self.regs[x as usize] < self.regs[y as usize] {
+            self.pc += 2;
+        }
+    }
+
+    // 0x9xy2
+    // compare reg x to reg y as unsigned 8-bit values
+    // skip next instruction if reg x > reg y
+    // (see lt_xy for why this skips instead of jumping to an address)
+    fn gtu_xy(&mut self, x: u8, y: u8) {
+        if self.regs[x as usize] > self.regs[y as usize] {
+            self.pc += 2;
+        }
+    }
+
     // 0xAnnn
     // set index register to nnn
     fn ld_i(&mut self, addr: u16) {
@@ -198,8 +413,9 @@ impl Machine {
 
     // 0xCx00
     // set index reg to I + reg x
-    fn add_i(&mut self, x: u8) {
-        self.i += self.regs[x as usize] as u16;
+    fn add_i(&mut self, x: u8) -> Result<(), RuntimeError> {
+        self.i = self.offset_addr(self.regs[x as usize] as u16)?;
+        Ok(())
     }
 
     // 0xDx00
@@ -208,28 +424,34 @@ impl Machine {
     // 100s digit at index reg
     // 10s digit at index reg + 1
     // 1s digit at index reg + 2
-    fn bcd(&mut self, x: u8) {
+    fn bcd(&mut self, x: u8) -> Result<(), RuntimeError> {
         let mut val = self.regs[x as usize];
         for i in (0..3).rev() {
-            self.mem[(self.i + i as u16) as usize] = val % 10;
+            let addr = self.offset_addr(i as u16)?;
+            self.mem.write(addr, val % 10)?;
             val /= 10;
         }
+        Ok(())
     }
 
     // 0xEx00
     // store registers 0 through x in memory starting at reg I
-    fn ld_0x(&mut self, x: u8) {
+    fn ld_0x(&mut self, x: u8) -> Result<(), RuntimeError> {
         for i in 0..x + 1 {
-            self.mem[(self.i + i as u16) as usize] = self.regs[i as usize];
+            let addr = self.offset_addr(i as u16)?;
+            self.mem.write(addr, self.regs[i as usize])?;
         }
+        Ok(())
     }
 
     // 0xFx00
     // store memory starting at reg I in registers 0 through x
-    fn ld_x0(&mut self, x: u8) {
+    fn ld_x0(&mut self, x: u8) -> Result<(), RuntimeError> {
         for i in 0..x + 1 {
-            self.regs[i as usize] = self.mem[(self.i + i as u16) as usize];
+            let addr = self.offset_addr(i as u16)?;
+            self.regs[i as usize] = self.mem.read(addr)?;
         }
+        Ok(())
     }
 
     // 0xFx01
@@ -243,71 +465,415 @@ impl Machine {
     // 0xFx02
     // prints memory starting at reg I to screen
     // ends at reg I + x
-    fn out_i(&mut self, x: u8) {
+    fn out_i(&mut self, x: u8) -> Result<(), RuntimeError> {
         for i in 0..x + 1 {
-            print!("{:X} ", self.mem[(self.i + i as u16) as usize]);
+            let addr = self.offset_addr(i as u16)?;
+            print!("{:X} ", self.mem.read(addr)?);
         }
+        Ok(())
     }
 
-    // 0xFFFF
-    // exit program
-    fn exit(&mut self) {
-        println!("Exiting...");
-        std::process::exit(1);
+    // add `offset` to the index register, rejecting the result outright
+    // if it overflows u16 rather than silently wrapping back into the
+    // valid address range
+    fn offset_addr(&self, offset: u16) -> Result<u16, RuntimeError> {
+        let addr = self.i as u32 + offset as u32;
+        u16::try_from(addr).map_err(|_| RuntimeError::MemoryAccess(self.i))
     }
 
-    fn err(&mut self, instr: Instruction) {
-        println!("Unknown instruction: {:X}", instr.0);
+    // 0xFx03
+    // read the delay timer into reg x
+    fn ld_vx_dt(&mut self, x: u8) {
+        self.regs[x as usize] = self.dt;
     }
 
+    // 0xFx04
+    // set the delay timer from reg x
+    fn ld_dt_vx(&mut self, x: u8) {
+        self.dt = self.regs[x as usize];
+    }
+
+    // 0xFx05
+    // set the sound timer from reg x
+    fn ld_st_vx(&mut self, x: u8) {
+        self.st = self.regs[x as usize];
+    }
+
+    // 0xFFFF
+    // exit program
+    fn exit(&mut self) -> HaltStatus {
+        HaltStatus::Exited
+    }
 
-    fn fetch(&mut self) -> Instruction {
-        if self.pc >= self.mem.len() as u16 {
-            panic!("PC out of bounds");
+    fn fetch(&mut self) -> Result<Instruction, RuntimeError> {
+        if self.pc as usize + 1 >= self.mem.len() {
+            return Err(RuntimeError::PcOutOfBounds(self.pc));
         }
-        let instr = Instruction::from_bytes([self.mem[self.pc as usize], self.mem[(self.pc + 1) as usize]]);
+        let instr = Instruction::from_bytes([self.mem.read(self.pc)?, self.mem.read(self.pc + 1)?]);
         self.pc += 2;
-        println!("{:X}", instr.0);
-        instr
-    }
-
-    fn dispatch(&mut self) {
-        let instr = self.fetch();
-        match instr.opcode() {
-            0x0 => self.ret(),
-            0x1 => self.jmp(instr.nnn()),
-            0x2 => self.call(instr.nnn()),
-            0x3 => self.eq(instr.x(), instr.kk()),
-            0x4 => self.neq(instr.x(), instr.kk()),
-            0x5 => self.eq_xy(instr.x(), instr.y()),
-            0x6 => self.ld(instr.x(), instr.kk()),
-            0x7 => self.add(instr.x(), instr.kk()),
-            0x8 => match instr.n() {
-                0x0 => self.ld_xy(instr.x(), instr.y()),
-                0x1 => self.or(instr.x(), instr.y()),
-                0x2 => self.and(instr.x(), instr.y()),
-                0x3 => self.xor(instr.x(), instr.y()),
-                0x4 => self.add_xy(instr.x(), instr.y()),
-                0x5 => self.sub_xy(instr.x(), instr.y()),
-                0x6 => self.shr(instr.x()),
-                0x7 => self.subn_xy(instr.x(), instr.y()),
-                0xE => self.shl(instr.x()),
-                _ => self.err(instr),
-            },
-            0x9 => self.neq_xy(instr.x(), instr.y()),
-            0xA => self.ld_i(instr.nnn()),
-            0xB => self.jmp_v0(instr.nnn()),
-            0xC => self.add_i(instr.x()),
-            0xD => self.bcd(instr.x()),
-            0xE => self.ld_0x(instr.x()),
-            0xF => match instr.n() {
-                0x0 => self.ld_x0(instr.x()),
-                0x1 => self.out(instr.x()),
-                0x2 => self.out_i(instr.x()),
-                0xF => self.exit(),
-                _ => self.err(instr),
-            },
-            _ => self.err(instr),
+        Ok(instr)
+    }
+
+    // fetch and execute exactly one instruction, returning `Some(status)`
+    // when the machine should stop running
+    fn dispatch(&mut self) -> Result<Option<HaltStatus>, RuntimeError> {
+        let instr = self.fetch()?;
+        if self.trace {
+            println!("{:X}", instr.0);
+        }
+        self.execute(instr)
+    }
+
+    // decode and run a single already-fetched instruction, looking up its
+    // handler in `OPCODE_TABLE` instead of re-deriving the opcode/nibble
+    // assignments here
+    fn execute(&mut self, instr: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+        let def = opcode_table::lookup(instr.opcode() as u8, instr.n())
+            .ok_or(RuntimeError::UnknownInstruction(instr.0))?;
+        (def.handler)(self, instr)
+    }
+}
+
+// thin adapters from the uniform `HandlerFn` signature to each handler
+// method's own argument list, so `OPCODE_TABLE` can hold one function
+// pointer per instruction regardless of its shape
+fn h_ret(m: &mut Machine, _i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ret()?;
+    Ok(None)
+}
+fn h_jmp(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.jmp(i.nnn());
+    Ok(None)
+}
+fn h_call(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.call(i.nnn())?;
+    Ok(None)
+}
+fn h_eq(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.eq(i.x(), i.kk());
+    Ok(None)
+}
+fn h_neq(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.neq(i.x(), i.kk());
+    Ok(None)
+}
+fn h_eq_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.eq_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_lt_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.lt_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_gt_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.gt_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_cmp_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.cmp_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_ld(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ld(i.x(), i.kk());
+    Ok(None)
+}
+fn h_add(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.add(i.x(), i.kk());
+    Ok(None)
+}
+fn h_ld_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ld_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_or(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.or(i.x(), i.y());
+    Ok(None)
+}
+fn h_and(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.and(i.x(), i.y());
+    Ok(None)
+}
+fn h_xor(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.xor(i.x(), i.y());
+    Ok(None)
+}
+fn h_add_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.add_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_sub_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.sub_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_shr(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.shr(i.x());
+    Ok(None)
+}
+fn h_subn_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.subn_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_shl(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.shl(i.x());
+    Ok(None)
+}
+fn h_neq_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.neq_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_ltu_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ltu_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_gtu_xy(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.gtu_xy(i.x(), i.y());
+    Ok(None)
+}
+fn h_ld_i(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ld_i(i.nnn());
+    Ok(None)
+}
+fn h_jmp_v0(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.jmp_v0(i.nnn());
+    Ok(None)
+}
+fn h_add_i(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.add_i(i.x())?;
+    Ok(None)
+}
+fn h_bcd(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.bcd(i.x())?;
+    Ok(None)
+}
+fn h_ld_0x(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ld_0x(i.x())?;
+    Ok(None)
+}
+fn h_ld_x0(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ld_x0(i.x())?;
+    Ok(None)
+}
+fn h_out(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.out(i.x());
+    Ok(None)
+}
+fn h_out_i(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.out_i(i.x())?;
+    Ok(None)
+}
+fn h_ld_vx_dt(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ld_vx_dt(i.x());
+    Ok(None)
+}
+fn h_ld_dt_vx(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ld_dt_vx(i.x());
+    Ok(None)
+}
+fn h_ld_st_vx(m: &mut Machine, i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    m.ld_st_vx(i.x());
+    Ok(None)
+}
+fn h_exit(m: &mut Machine, _i: Instruction) -> Result<Option<HaltStatus>, RuntimeError> {
+    Ok(Some(m.exit()))
+}
+
+// the authoritative opcode/nibble -> mnemonic/operand-shape assignments,
+// shared with `asm::{assemble, disassemble}` via `opcode_table::lookup`
+// and `opcode_table::lookup_mnemonic` so the two never drift apart
+pub(crate) static OPCODE_TABLE: &[OpcodeDef] = &[
+    OpcodeDef { mnemonic: "ret", opcode: 0x0, n: None, shape: OperandShape::None { filler: 0x000 }, handler: h_ret },
+    OpcodeDef { mnemonic: "jmp", opcode: 0x1, n: None, shape: OperandShape::Addr, handler: h_jmp },
+    OpcodeDef { mnemonic: "call", opcode: 0x2, n: None, shape: OperandShape::Addr, handler: h_call },
+    OpcodeDef { mnemonic: "eq", opcode: 0x3, n: None, shape: OperandShape::RegByte, handler: h_eq },
+    OpcodeDef { mnemonic: "neq", opcode: 0x4, n: None, shape: OperandShape::RegByte, handler: h_neq },
+    OpcodeDef { mnemonic: "eq_xy", opcode: 0x5, n: Some(0x0), shape: OperandShape::RegReg, handler: h_eq_xy },
+    OpcodeDef { mnemonic: "lt_xy", opcode: 0x5, n: Some(0x1), shape: OperandShape::RegReg, handler: h_lt_xy },
+    OpcodeDef { mnemonic: "gt_xy", opcode: 0x5, n: Some(0x2), shape: OperandShape::RegReg, handler: h_gt_xy },
+    OpcodeDef { mnemonic: "cmp_xy", opcode: 0x5, n: Some(0x3), shape: OperandShape::RegReg, handler: h_cmp_xy },
+    OpcodeDef { mnemonic: "ld", opcode: 0x6, n: None, shape: OperandShape::RegByte, handler: h_ld },
+    OpcodeDef { mnemonic: "add", opcode: 0x7, n: None, shape: OperandShape::RegByte, handler: h_add },
+    OpcodeDef { mnemonic: "ld_xy", opcode: 0x8, n: Some(0x0), shape: OperandShape::RegReg, handler: h_ld_xy },
+    OpcodeDef { mnemonic: "or", opcode: 0x8, n: Some(0x1), shape: OperandShape::RegReg, handler: h_or },
+    OpcodeDef { mnemonic: "and", opcode: 0x8, n: Some(0x2), shape: OperandShape::RegReg, handler: h_and },
+    OpcodeDef { mnemonic: "xor", opcode: 0x8, n: Some(0x3), shape: OperandShape::RegReg, handler: h_xor },
+    OpcodeDef { mnemonic: "add_xy", opcode: 0x8, n: Some(0x4), shape: OperandShape::RegReg, handler: h_add_xy },
+    OpcodeDef { mnemonic: "sub_xy", opcode: 0x8, n: Some(0x5), shape: OperandShape::RegReg, handler: h_sub_xy },
+    OpcodeDef { mnemonic: "shr", opcode: 0x8, n: Some(0x6), shape: OperandShape::Reg, handler: h_shr },
+    OpcodeDef { mnemonic: "subn_xy", opcode: 0x8, n: Some(0x7), shape: OperandShape::RegReg, handler: h_subn_xy },
+    OpcodeDef { mnemonic: "shl", opcode: 0x8, n: Some(0xE), shape: OperandShape::Reg, handler: h_shl },
+    OpcodeDef { mnemonic: "neq_xy", opcode: 0x9, n: Some(0x0), shape: OperandShape::RegReg, handler: h_neq_xy },
+    OpcodeDef { mnemonic: "ltu_xy", opcode: 0x9, n: Some(0x1), shape: OperandShape::RegReg, handler: h_ltu_xy },
+    OpcodeDef { mnemonic: "gtu_xy", opcode: 0x9, n: Some(0x2), shape: OperandShape::RegReg, handler: h_gtu_xy },
+    OpcodeDef { mnemonic: "ld_i", opcode: 0xA, n: None, shape: OperandShape::Addr, handler: h_ld_i },
+    OpcodeDef { mnemonic: "jmp_v0", opcode: 0xB, n: None, shape: OperandShape::Addr, handler: h_jmp_v0 },
+    OpcodeDef { mnemonic: "add_i", opcode: 0xC, n: None, shape: OperandShape::Reg, handler: h_add_i },
+    OpcodeDef { mnemonic: "bcd", opcode: 0xD, n: None, shape: OperandShape::Reg, handler: h_bcd },
+    OpcodeDef { mnemonic: "ld_0x", opcode: 0xE, n: None, shape: OperandShape::Reg, handler: h_ld_0x },
+    OpcodeDef { mnemonic: "ld_x0", opcode: 0xF, n: Some(0x0), shape: OperandShape::Reg, handler: h_ld_x0 },
+    OpcodeDef { mnemonic: "out", opcode: 0xF, n: Some(0x1), shape: OperandShape::Reg, handler: h_out },
+    OpcodeDef { mnemonic: "out_i", opcode: 0xF, n: Some(0x2), shape: OperandShape::Reg, handler: h_out_i },
+    OpcodeDef { mnemonic: "ld_vx_dt", opcode: 0xF, n: Some(0x3), shape: OperandShape::Reg, handler: h_ld_vx_dt },
+    OpcodeDef { mnemonic: "ld_dt_vx", opcode: 0xF, n: Some(0x4), shape: OperandShape::Reg, handler: h_ld_dt_vx },
+    OpcodeDef { mnemonic: "ld_st_vx", opcode: 0xF, n: Some(0x5), shape: OperandShape::Reg, handler: h_ld_st_vx },
+    OpcodeDef { mnemonic: "exit", opcode: 0xF, n: Some(0xF), shape: OperandShape::None { filler: 0xFFF }, handler: h_exit },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn machine_with_program(words: &[u16]) -> Machine {
+        let mut m = Machine::new(MachineConfig::default());
+        let bytes: Vec<u8> = words.iter().flat_map(|&w| Instruction(w).as_bytes()).collect();
+        m.load(&bytes).unwrap();
+        m
+    }
+
+    #[test]
+    fn ret_errors_on_empty_stack() {
+        let mut m = machine_with_program(&[0x0000]);
+        assert_eq!(m.step().unwrap_err(), RuntimeError::StackUnderflow);
+    }
+
+    #[test]
+    fn call_errors_when_stack_is_full() {
+        // `call 0x200` recurses into itself, growing the stack by one frame
+        // per step until the 16-entry stack has no room left
+        let mut m = machine_with_program(&[0x2200]);
+        for _ in 0..15 {
+            m.step().expect("stack has room");
         }
+        assert_eq!(m.step().unwrap_err(), RuntimeError::StackOverflow);
+    }
+
+    #[test]
+    fn fetch_errors_when_pc_runs_past_memory() {
+        let mut m = Machine::new(MachineConfig { memory_size: 4 });
+        assert_eq!(m.step().unwrap_err(), RuntimeError::PcOutOfBounds(PROGRAM_START));
+    }
+
+    #[test]
+    fn execute_errors_on_unknown_instruction() {
+        // opcode 0x8 only defines sub-ops 0x0-0x7 and 0xE; 0x8 itself is unused
+        let mut m = machine_with_program(&[0x8008]);
+        assert_eq!(m.step().unwrap_err(), RuntimeError::UnknownInstruction(0x8008));
+    }
+
+    #[test]
+    fn breakpoint_halts_run_before_the_instruction_executes() {
+        // ld v0 1; add v0 1; exit
+        let mut m = machine_with_program(&[0x6001, 0x7001, 0xFFFF]);
+        m.add_breakpoint(PROGRAM_START + 2);
+        assert_eq!(m.run().unwrap(), HaltStatus::Halted);
+        assert_eq!(m.regs[0], 1, "should have stopped before `add` executed");
+    }
+
+    #[test]
+    fn step_ignores_breakpoints() {
+        let mut m = machine_with_program(&[0x6001, 0x7001, 0xFFFF]);
+        m.add_breakpoint(PROGRAM_START + 2);
+        m.step().unwrap();
+        m.step().unwrap();
+        assert_eq!(m.regs[0], 2, "step should run through the breakpoint");
+    }
+
+    #[test]
+    fn dump_does_not_panic() {
+        let m = machine_with_program(&[0xFFFF]);
+        m.dump();
+    }
+
+    #[test]
+    fn tick_timers_decrements_once_per_60hz_period() {
+        let mut m = machine_with_program(&[0xFFFF]);
+        m.dt = 10;
+        m.st = 10;
+        m.tick_timers(TIMER_PERIOD);
+        assert_eq!((m.dt, m.st), (9, 9));
+    }
+
+    #[test]
+    fn tick_timers_only_fires_for_whole_elapsed_periods() {
+        let mut m = machine_with_program(&[0xFFFF]);
+        m.dt = 10;
+        m.tick_timers(TIMER_PERIOD / 2);
+        assert_eq!(m.dt, 10, "half a period shouldn't be enough to decrement yet");
+        m.tick_timers(TIMER_PERIOD / 2);
+        assert_eq!(m.dt, 9, "the other half completes the period");
+    }
+
+    #[test]
+    fn tick_timers_saturates_at_zero() {
+        let mut m = machine_with_program(&[0xFFFF]);
+        m.dt = 0;
+        m.tick_timers(TIMER_PERIOD * 5);
+        assert_eq!(m.dt, 0);
+    }
+
+    #[test]
+    fn set_ips_cap_of_zero_is_treated_as_unthrottled() {
+        let mut m = machine_with_program(&[0xFFFF]);
+        m.set_ips_cap(Some(0));
+        assert_eq!(m.ips_cap, None);
+        assert_eq!(m.run().unwrap(), HaltStatus::Exited);
+    }
+
+    #[test]
+    fn cmp_xy_reports_less_equal_greater_as_0_1_2() {
+        let mut m = machine_with_program(&[0xFFFF]);
+        m.regs[0] = (-1i8) as u8;
+        m.regs[1] = 1;
+        m.cmp_xy(0, 1);
+        assert_eq!(m.regs[0xF], 0, "less than");
+
+        m.regs[0] = 5;
+        m.regs[1] = 5;
+        m.cmp_xy(0, 1);
+        assert_eq!(m.regs[0xF], 1, "equal");
+
+        m.regs[0] = 1;
+        m.regs[1] = (-1i8) as u8;
+        m.cmp_xy(0, 1);
+        assert_eq!(m.regs[0xF], 2, "greater than");
+    }
+
+    #[test]
+    fn lt_xy_skips_using_signed_comparison() {
+        let mut m = machine_with_program(&[0xFFFF]);
+        let before = m.pc;
+        m.regs[0] = (-1i8) as u8; // -1 < 1 as signed, but 0xff > 0x01 as unsigned
+        m.regs[1] = 1;
+        m.lt_xy(0, 1);
+        assert_eq!(m.pc, before + 2);
+    }
+
+    #[test]
+    fn gt_xy_does_not_skip_when_not_greater() {
+        let mut m = machine_with_program(&[0xFFFF]);
+        let before = m.pc;
+        m.regs[0] = 1;
+        m.regs[1] = 1;
+        m.gt_xy(0, 1);
+        assert_eq!(m.pc, before);
+    }
+
+    #[test]
+    fn ltu_xy_skips_using_unsigned_comparison() {
+        let mut m = machine_with_program(&[0xFFFF]);
+        let before = m.pc;
+        m.regs[0] = 1;
+        m.regs[1] = 0xff; // -1 as signed, but greater than 1 as unsigned
+        m.ltu_xy(0, 1);
+        assert_eq!(m.pc, before + 2);
+    }
+
+    #[test]
+    fn gtu_xy_skips_using_unsigned_comparison() {
+        let mut m = machine_with_program(&[0xFFFF]);
+        let before = m.pc;
+        m.regs[0] = 0xff;
+        m.regs[1] = 1;
+        m.gtu_xy(0, 1);
+        assert_eq!(m.pc, before + 2);
     }
-}
\ No newline at end of file
+}
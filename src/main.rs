@@ -1,12 +1,68 @@
+mod asm;
 mod instruction;
 mod machine;
+mod memory;
+mod opcode_table;
+
+// a tiny demo program: add two bytes, print the result, then ring the bell
+const SOURCE: &str = "\
+    ld v0 0x11\n\
+    ld v1 0x22\n\
+    add_xy v0 v1\n\
+    out v0\n\
+    ld v2 0x03\n\
+    ld_st_vx v2\n\
+    exit\n";
 
 fn main() {
-    let mut machine = machine::Machine::new();
-    machine.load(&[0x60, 0x11,
-                            0x61, 0x22,
-                            0x80, 0x14,   
-                            0xF0, 0x01,
-                            0xFF, 0xFF]);
-    machine.run();
+    let program = match asm::assemble(SOURCE) {
+        Ok(bytes) => bytes,
+        Err(err) => {
+            eprintln!("assemble error: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    println!("disassembly:");
+    for line in asm::disassemble(&program) {
+        println!("  {}", line);
+    }
+
+    let mut machine = machine::Machine::new(machine::MachineConfig::default());
+    if let Err(err) = machine.load(&program) {
+        eprintln!("load error: {:?}", err);
+        std::process::exit(1);
+    }
+
+    // run with tracing on and a breakpoint on `out v0`, so we can inspect
+    // state right before it prints, then step past it by hand
+    machine.set_trace(true);
+    machine.add_breakpoint(0x206);
+    match machine.run() {
+        Ok(machine::HaltStatus::Halted) => {
+            println!("hit breakpoint at 0x206:");
+            machine.dump();
+            machine.remove_breakpoint(0x206);
+            if let Err(err) = machine.step() {
+                eprintln!("runtime error: {:?}", err);
+                std::process::exit(1);
+            }
+        }
+        Ok(machine::HaltStatus::Exited) => {}
+        Err(err) => {
+            eprintln!("runtime error: {:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    // throttle the rest of the run and check whether it leaves the sound
+    // timer active, the way a host driving audio playback would
+    machine.set_ips_cap(Some(500));
+    if let Err(err) = machine.run() {
+        eprintln!("runtime error: {:?}", err);
+        std::process::exit(1);
+    }
+    if machine.sound_active() {
+        println!("bell!");
+    }
 }
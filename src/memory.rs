@@ -0,0 +1,134 @@
+// Page-based memory backing `Machine`, sized by `MachineConfig` instead of
+// a fixed 4 KB array. Pages are allocated up front for the configured size,
+// so out-of-range access is always a recoverable `RuntimeError` rather than
+// a silent wrap or a panic.
+use crate::machine::RuntimeError;
+
+const PAGE_SIZE: usize = 4096;
+
+struct Page {
+    data: [u8; PAGE_SIZE],
+}
+
+impl Page {
+    fn new() -> Self {
+        Page { data: [0; PAGE_SIZE] }
+    }
+}
+
+pub struct Memory {
+    size: usize,
+    pages: Vec<Page>,
+}
+
+impl Memory {
+    pub fn new(size: usize) -> Self {
+        let page_count = size.div_ceil(PAGE_SIZE).max(1);
+        Memory {
+            size,
+            pages: (0..page_count).map(|_| Page::new()).collect(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    pub fn read(&self, addr: u16) -> Result<u8, RuntimeError> {
+        let addr = addr as usize;
+        if addr >= self.size {
+            return Err(RuntimeError::MemoryAccess(addr as u16));
+        }
+        Ok(self.pages[addr / PAGE_SIZE].data[addr % PAGE_SIZE])
+    }
+
+    pub fn write(&mut self, addr: u16, byte: u8) -> Result<(), RuntimeError> {
+        let idx = addr as usize;
+        if idx >= self.size {
+            return Err(RuntimeError::MemoryAccess(addr));
+        }
+        self.pages[idx / PAGE_SIZE].data[idx % PAGE_SIZE] = byte;
+        Ok(())
+    }
+
+    pub fn read_slice(&self, addr: u16, len: usize) -> Result<Vec<u8>, RuntimeError> {
+        (0..len)
+            .map(|offset| {
+                let at = u16::try_from(addr as usize + offset).map_err(|_| RuntimeError::MemoryAccess(addr))?;
+                self.read(at)
+            })
+            .collect()
+    }
+
+    pub fn write_slice(&mut self, addr: u16, bytes: &[u8]) -> Result<(), RuntimeError> {
+        for (offset, &byte) in bytes.iter().enumerate() {
+            let at = u16::try_from(addr as usize + offset).map_err(|_| RuntimeError::MemoryAccess(addr))?;
+            self.write(at, byte)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_write_round_trips_within_a_page() {
+        let mut mem = Memory::new(PAGE_SIZE * 2);
+        mem.write(10, 0xAB).unwrap();
+        assert_eq!(mem.read(10).unwrap(), 0xAB);
+    }
+
+    #[test]
+    fn write_slice_spans_a_page_boundary() {
+        let mut mem = Memory::new(PAGE_SIZE * 2);
+        let boundary = PAGE_SIZE as u16 - 1;
+        mem.write_slice(boundary, &[0x11, 0x22, 0x33]).unwrap();
+        assert_eq!(mem.read(boundary).unwrap(), 0x11);
+        assert_eq!(mem.read(boundary + 1).unwrap(), 0x22);
+        assert_eq!(mem.read(boundary + 2).unwrap(), 0x33);
+    }
+
+    #[test]
+    fn read_slice_spans_a_page_boundary() {
+        let mut mem = Memory::new(PAGE_SIZE * 2);
+        let boundary = PAGE_SIZE as u16 - 2;
+        mem.write_slice(boundary, &[0xDE, 0xAD, 0xBE, 0xEF]).unwrap();
+        let read = mem.read_slice(boundary, 4).unwrap();
+        assert_eq!(read, vec![0xDE, 0xAD, 0xBE, 0xEF]);
+    }
+
+    #[test]
+    fn read_past_configured_size_is_an_error() {
+        let mem = Memory::new(PAGE_SIZE);
+        assert_eq!(mem.read(PAGE_SIZE as u16), Err(RuntimeError::MemoryAccess(PAGE_SIZE as u16)));
+    }
+
+    #[test]
+    fn write_past_configured_size_is_an_error() {
+        let mut mem = Memory::new(PAGE_SIZE);
+        assert_eq!(
+            mem.write(PAGE_SIZE as u16, 0x01),
+            Err(RuntimeError::MemoryAccess(PAGE_SIZE as u16))
+        );
+    }
+
+    #[test]
+    fn size_smaller_than_a_page_still_allocates_one_page() {
+        let mem = Memory::new(16);
+        assert_eq!(mem.len(), 16);
+        assert!(mem.read(16).is_err());
+        assert!(mem.read(15).is_ok());
+    }
+
+    #[test]
+    fn is_empty_reflects_the_configured_size() {
+        assert!(Memory::new(0).is_empty());
+        assert!(!Memory::new(1).is_empty());
+    }
+}
@@ -0,0 +1,47 @@
+// The single source of truth for how 16-bit words map to instructions.
+// `machine::execute` and `asm::{assemble, disassemble}` both walk the same
+// `OpcodeDef` table instead of keeping their own hand-written copies of the
+// opcode/nibble assignments in sync.
+use crate::instruction::Instruction;
+use crate::machine::{HaltStatus, Machine, RuntimeError};
+
+// how an instruction's operands are laid out in the low 12 bits, and in
+// turn how the assembler should parse/print them
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum OperandShape {
+    // no operands; the low 12 bits are a fixed literal (e.g. `ret`, `exit`)
+    None { filler: u16 },
+    // a single 12-bit address (`nnn`), possibly a label
+    Addr,
+    // a register and an 8-bit immediate (`x`, `kk`)
+    RegByte,
+    // two registers (`x`, `y`); `n` selects the specific operation
+    RegReg,
+    // a single register (`x`); `n` selects the specific operation
+    Reg,
+}
+
+pub(crate) type HandlerFn = fn(&mut Machine, Instruction) -> Result<Option<HaltStatus>, RuntimeError>;
+
+pub(crate) struct OpcodeDef {
+    pub mnemonic: &'static str,
+    pub opcode: u8,
+    // the low nibble, when it selects between multiple operations sharing
+    // the same opcode (e.g. 0x8xy4 vs 0x8xy5); `None` when the whole low
+    // byte/word is operand data instead
+    pub n: Option<u8>,
+    pub shape: OperandShape,
+    pub handler: HandlerFn,
+}
+
+// find the definition for a decoded instruction
+pub(crate) fn lookup(opcode: u8, n: u8) -> Option<&'static OpcodeDef> {
+    crate::machine::OPCODE_TABLE
+        .iter()
+        .find(|def| def.opcode == opcode && (def.n.is_none() || def.n == Some(n)))
+}
+
+// find the definition for an assembler mnemonic
+pub(crate) fn lookup_mnemonic(mnemonic: &str) -> Option<&'static OpcodeDef> {
+    crate::machine::OPCODE_TABLE.iter().find(|def| def.mnemonic == mnemonic)
+}